@@ -1,125 +1,388 @@
+use std::path::PathBuf;
 use std::time::Instant;
 
 use crossbeam_channel::{unbounded, Receiver, Sender};
 use eframe::{egui, epi};
+use rand::Rng;
 
+mod player;
+mod transcode;
+mod voice;
 mod voices;
+mod wav;
+
+use transcode::OutputFormat;
+use voice::VoiceConfig;
+
+use player::AudioPlayer;
 
 /// The number of seconds after which the connection will be dropped.
 /// This is required since the TTS service sometimes hangs up forever for no apparent reason.
 pub const TTS_TIMEOUT_SECONDS: u64 = 180;
 
+/// The maximum number of attempts made for a single prompt before giving up.
+/// The upstream service occasionally hangs or returns a 5xx, so transient
+/// failures are retried rather than surfaced immediately to the user.
+pub const MAX_ATTEMPTS: u32 = 3;
+
+/// The base backoff delay, doubled after every failed attempt.
+const BACKOFF_BASE: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// The maximum number of prompts rendered concurrently. The async runtime
+/// drains the queue in parallel up to this bound rather than strictly serially.
+const MAX_CONCURRENT_RENDERS: usize = 3;
+
+/// The maximum number of characters sent in a single request when long-prompt
+/// chunking is enabled. Chunks are split at sentence boundaries and stitched
+/// back together, keeping each request comfortably under the service's cap.
+pub const MAX_CHUNK_CHARS: usize = 300;
+
+/// A stable identifier assigned to each enqueued prompt so that out-of-band
+/// results can be matched back to the row that produced them.
+type JobId = u64;
+
 /// A text prompt submitted by the user.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct TtsPrompt {
+    /// The id of the job this prompt belongs to.
+    id: JobId,
     /// The voice key to use
     voice: &'static str,
     /// The text to speak.
     prompt: String,
     /// The name of the resulting .wav file.
     filename: String,
+    /// Whether to split an over-long prompt into sentence-sized chunks and
+    /// stitch the returned WAVs back into a single file.
+    chunked: bool,
+    /// The format the saved file should be transcoded to.
+    format: OutputFormat,
+    /// If set, also stream the rendered audio into the configured voice channel.
+    voice_sink: Option<VoiceConfig>,
+}
+/// The path of the file (in the selected output format) a successful render was saved to, handed back
+/// so the UI thread can load it into the [`AudioPlayer`] for preview.
+type TtsResult = Result<PathBuf, Error>;
+
+/// An update emitted by the downloader thread, tagged with the [`JobId`] of the
+/// prompt it refers to so the UI can reconcile it against the queue.
+enum TtsUpdate {
+    /// The downloader is (re)attempting the job; the payload is the 1-based
+    /// attempt number so the UI can show which retry is in flight.
+    Attempt(JobId, u32),
+    /// The downloader has finished the job with the given outcome.
+    Finished(JobId, TtsResult),
 }
-type TtsResult = Result<(), Error>;
 
 /// This struct is used by the GUI to submit prompts.
 struct TtsSubmitter {
     /// The channel to submit the prompt.
     prompt_tx: Sender<TtsPrompt>,
-    /// The channel to receive the result.
-    result_rx: Receiver<TtsResult>,
+    /// The channel to receive per-job updates.
+    update_rx: Receiver<TtsUpdate>,
 }
 
 /// This struct is used by the downloader thread to receive prompts and send back results.
 struct TtsReceiver {
     /// The channel to receive prompts.
     prompt_rx: Receiver<TtsPrompt>,
-    /// The channel to send back results.
-    result_tx: Sender<TtsResult>,
+    /// The channel to send back per-job updates.
+    update_tx: Sender<TtsUpdate>,
+}
+
+/// Computes the backoff delay before the next retry: [`BACKOFF_BASE`] after the
+/// first failed attempt, doubled after each further one, with ±25% random jitter so concurrent
+/// clients don't all hammer the service in lockstep.
+fn backoff_delay(attempt: u32) -> std::time::Duration {
+    let base = BACKOFF_BASE.as_secs_f64() * 2f64.powi(attempt.saturating_sub(1) as i32);
+    let jitter = rand::thread_rng().gen_range(0.75..=1.25);
+    std::time::Duration::from_secs_f64(base * jitter)
+}
+
+/// Renders a single chunk of text, retrying transient failures (timeouts and
+/// 5xx responses) with [`backoff_delay`]. `on_attempt` is invoked with the
+/// 1-based attempt number before each try so the caller can report progress.
+/// Returns the raw WAV bytes on success, or the [`Error`] of the last attempt
+/// made (a non-retriable failure ends the loop at once).
+async fn render_chunk(
+    client: &reqwest::Client,
+    headers: &reqwest::header::HeaderMap,
+    voice: &'static str,
+    text: &str,
+    mut on_attempt: impl FnMut(u32),
+) -> Result<Vec<u8>, Error> {
+    for attempt in 1..=MAX_ATTEMPTS {
+        on_attempt(attempt);
+        log::info!("Making a request to the api (attempt {}/{})...", attempt, MAX_ATTEMPTS);
+
+        let res = client
+            .post("https://mumble.stream/speak")
+            .headers(headers.clone())
+            .body(format!(
+                "{{\"speaker\":\"{}\",\"text\":\"{}\"}}",
+                voices::TTS_VOICES[voice],
+                text
+            ))
+            .send()
+            .await;
+        log::info!(
+            "Received a response with the code: {:#?}",
+            res.as_ref().map(|r| r.status())
+        );
+        log::debug!("Received a response: {:#?}", res);
+
+        // Whether this outcome is worth another attempt.
+        let retriable = match &res {
+            Ok(r) => r.status().is_server_error(),
+            Err(e) => e.is_timeout(),
+        };
+
+        let outcome = match res {
+            Ok(r) => {
+                if r.status().is_success() {
+                    r.bytes().await.map(|b| b.to_vec()).map_err(|e| Error {
+                        title: "Error: Failed to read the audio".to_string(),
+                        message: e.to_string(),
+                        should_exit: false,
+                        acknowledged: false,
+                    })
+                } else {
+                    let status = r.status();
+                    Err(Error {
+                        title: format!("Error: The server's response wasn't a success ({})", status),
+                        message: match r
+                            .text()
+                            .await
+                            .unwrap_or_else(|_| "<Failed to get the error message>".into())
+                        {
+                            text if text.is_empty() => "(response was empty)".into(),
+                            rest => rest,
+                        },
+                        should_exit: false,
+                        acknowledged: false,
+                    })
+                }
+            }
+            Err(e) => Err(Error {
+                title: "Error: Failed to generate audio".to_string(),
+                message: e.to_string(),
+                should_exit: false,
+                acknowledged: false,
+            }),
+        };
+
+        if outcome.is_ok() || !retriable || attempt == MAX_ATTEMPTS {
+            return outcome;
+        }
+
+        let delay = backoff_delay(attempt);
+        log::warn!(
+            "Attempt {}/{} failed; retrying in {:.1}s",
+            attempt,
+            MAX_ATTEMPTS,
+            delay.as_secs_f64()
+        );
+        tokio::time::sleep(delay).await;
+    }
+
+    unreachable!("the retry loop always returns on the final attempt")
+}
+
+/// Renders a whole prompt: splits it into chunks, renders each over the shared
+/// async client, stitches and transcodes the result, and writes the file. The
+/// blocking encode/write step is offloaded to a blocking thread so the
+/// async workers stay free for network I/O.
+async fn process_prompt(
+    client: reqwest::Client,
+    headers: reqwest::header::HeaderMap,
+    update_tx: Sender<TtsUpdate>,
+    prompt: TtsPrompt,
+) {
+    log::info!("Received a new prompt: {:#?}", prompt);
+
+    // A long prompt is split at sentence boundaries and rendered chunk by chunk;
+    // the returned WAVs are stitched into a single file.
+    let texts = if prompt.chunked {
+        split_into_chunks(&prompt.prompt, MAX_CHUNK_CHARS)
+    } else {
+        vec![prompt.prompt.clone()]
+    };
+
+    let mut payloads = Vec::with_capacity(texts.len());
+    let mut failure = None;
+    for text in &texts {
+        let result = render_chunk(&client, &headers, prompt.voice, text, |attempt| {
+            let _ = update_tx.send(TtsUpdate::Attempt(prompt.id, attempt));
+        })
+        .await;
+        match result {
+            Ok(bytes) => payloads.push(bytes),
+            Err(e) => {
+                failure = Some(e);
+                break;
+            }
+        }
+    }
+
+    let result = match failure {
+        Some(e) => Err(e),
+        None => match stitch(payloads) {
+            Ok(wav_bytes) => {
+                // Optionally branch the audio to a voice channel. Streaming is
+                // an extra sink, so a failure here is logged, not fatal.
+                if let Some(config) = &prompt.voice_sink {
+                    if let Err(e) = voice::stream_wav(config, &wav_bytes).await {
+                        log::warn!("Failed to stream to the voice channel: {}", e);
+                    }
+                }
+                let prompt = prompt.clone();
+                tokio::task::spawn_blocking(move || write_output(&prompt, wav_bytes))
+                    .await
+                    .unwrap_or_else(|e| {
+                        Err(Error {
+                            title: "Error: The render task panicked".to_string(),
+                            message: e.to_string(),
+                            should_exit: false,
+                            acknowledged: false,
+                        })
+                    })
+            }
+            Err(e) => Err(e),
+        },
+    };
+
+    let _ = update_tx.send(TtsUpdate::Finished(prompt.id, result));
+}
+
+/// Stitches (or passes through) the rendered payloads into a single WAV blob.
+fn stitch(mut payloads: Vec<Vec<u8>>) -> Result<Vec<u8>, Error> {
+    if payloads.len() == 1 {
+        Ok(payloads.pop().unwrap())
+    } else {
+        wav::stitch(&payloads).map_err(|message| Error {
+            title: "Error: Failed to stitch the audio chunks".to_string(),
+            message,
+            should_exit: false,
+            acknowledged: false,
+        })
+    }
+}
+
+/// Transcodes the WAV blob to the requested format and writes the output file.
+/// This is synchronous and CPU-bound, so callers run it on a blocking thread.
+fn write_output(prompt: &TtsPrompt, wav_bytes: Vec<u8>) -> TtsResult {
+    let bytes = transcode::encode(prompt.format, wav_bytes).map_err(|message| Error {
+        title: "Error: Failed to transcode the audio".to_string(),
+        message,
+        should_exit: false,
+        acknowledged: false,
+    })?;
+    std::fs::write(&prompt.filename, bytes)
+        .map(|_| PathBuf::from(&prompt.filename))
+        .map_err(|e| Error {
+            title: "Error: Failed to save the audio".to_string(),
+            message: e.to_string(),
+            should_exit: false,
+            acknowledged: false,
+        })
+}
+
+/// Splits a prompt into chunks no longer than `budget` characters, breaking at
+/// sentence boundaries (`.`, `!`, `?`). A single sentence that exceeds the
+/// budget is emitted on its own rather than being cut mid-word.
+fn split_into_chunks(prompt: &str, budget: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+
+    for sentence in split_sentences(prompt) {
+        // Start a new chunk if appending this sentence would overflow.
+        if !current.is_empty() && current.len() + sentence.len() > budget {
+            chunks.push(std::mem::take(&mut current));
+        }
+        current.push_str(&sentence);
+    }
+    if !current.trim().is_empty() {
+        chunks.push(current);
+    }
+    if chunks.is_empty() {
+        chunks.push(prompt.to_owned());
+    }
+    chunks
+}
+
+/// Splits text into sentences, keeping the terminating punctuation with each.
+fn split_sentences(text: &str) -> Vec<String> {
+    let mut sentences = Vec::new();
+    let mut current = String::new();
+    for c in text.chars() {
+        current.push(c);
+        if matches!(c, '.' | '!' | '?') {
+            sentences.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.trim().is_empty() {
+        sentences.push(current);
+    }
+    sentences
 }
 
 /// Spawns a download thread and returns a struct holding the prompt sender and result receiver.
 /// The thread will be stopped automatically when the sender is destroyed.
 fn spawn_downloader_thread() -> TtsSubmitter {
     let (prompt_tx, prompt_rx) = unbounded();
-    let (result_tx, result_rx) = unbounded();
+    let (update_tx, update_rx) = unbounded();
 
     let submitter = TtsSubmitter {
         prompt_tx,
-        result_rx,
+        update_rx,
     };
     let receiver = TtsReceiver {
         prompt_rx,
-        result_tx,
+        update_tx,
     };
 
+    // The runtime lives on its own OS thread so the egui UI thread is never
+    // blocked; prompts are rendered as concurrent async tasks, bounded by a
+    // semaphore, and results still flow back over the same channels.
     std::thread::spawn(move || {
-        let client = reqwest::blocking::Client::builder()
-            .timeout(std::time::Duration::from_secs(TTS_TIMEOUT_SECONDS))
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
             .build()
             .unwrap();
-        let mut headers = reqwest::header::HeaderMap::new();
-        headers.insert(
-            reqwest::header::CONTENT_TYPE,
-            reqwest::header::HeaderValue::from_static("application/json"),
-        );
 
-        while let Ok(prompt) = receiver.prompt_rx.recv() {
-            log::info!("Received a new prompt: {:#?}", prompt);
-            log::info!("Making a request to the api...");
-
-            let res = client
-                .post("https://mumble.stream/speak")
-                .headers(headers.clone())
-                .body(format!(
-                    "{{\"speaker\":\"{}\",\"text\":\"{}\"}}",
-                    voices::TTS_VOICES[prompt.voice],
-                    prompt.prompt
-                ))
-                .send();
-            log::info!(
-                "Received a response with the code: {:#?}",
-                res.as_ref().map(|r| r.status())
+        runtime.block_on(async move {
+            let client = reqwest::Client::builder()
+                .timeout(std::time::Duration::from_secs(TTS_TIMEOUT_SECONDS))
+                .build()
+                .unwrap();
+            let mut headers = reqwest::header::HeaderMap::new();
+            headers.insert(
+                reqwest::header::CONTENT_TYPE,
+                reqwest::header::HeaderValue::from_static("application/json"),
             );
-            log::debug!("Received a response: {:#?}", res);
-            let result = match res {
-                Ok(r) => {
-                    if r.status().is_success() {
-                        match r.bytes().map(|b| std::fs::write(&prompt.filename, b)) {
-                            Ok(_) => Ok(()),
-                            Err(e) => Err(Error {
-                                title: "Error: Failed to save the audio".to_string(),
-                                message: e.to_string(),
-                                should_exit: false,
-                                acknowledged: false,
-                            }),
-                        }
-                    } else {
-                        Err(Error {
-                            title: format!(
-                                "Error: The server's response wasn't a success ({})",
-                                r.status()
-                            ),
-                            message: match r
-                                .text()
-                                .unwrap_or_else(|_| "<Failed to get the error message>".into())
-                            {
-                                text if text.is_empty() => "(response was empty)".into(),
-                                rest => rest,
-                            },
-                            should_exit: false,
-                            acknowledged: false,
-                        })
-                    }
-                }
-                Err(e) => Err(Error {
-                    title: "Error: Failed to generate audio".to_string(),
-                    message: e.to_string(),
-                    should_exit: false,
-                    acknowledged: false,
-                }),
-            };
-            let _ = receiver.result_tx.send(result);
-        }
+
+            let semaphore = std::sync::Arc::new(tokio::sync::Semaphore::new(MAX_CONCURRENT_RENDERS));
+            let prompt_rx = receiver.prompt_rx;
+
+            loop {
+                // Block for the next prompt on a dedicated thread so the async
+                // workers keep driving the in-flight renders.
+                let rx = prompt_rx.clone();
+                let prompt = match tokio::task::spawn_blocking(move || rx.recv()).await {
+                    Ok(Ok(prompt)) => prompt,
+                    _ => break,
+                };
+
+                let permit = semaphore.clone().acquire_owned().await.unwrap();
+                let client = client.clone();
+                let headers = headers.clone();
+                let update_tx = receiver.update_tx.clone();
+                tokio::spawn(async move {
+                    let _permit = permit;
+                    process_prompt(client, headers, update_tx, prompt).await;
+                });
+            }
+        });
     });
 
     submitter
@@ -139,30 +402,49 @@ struct Error {
 
 /// The current state of the GUI.
 #[derive(Clone, Copy, PartialEq)]
-enum Status {
-    /// Idle, the program is waiting for a prompt.
-    Idle,
-    /// Processing, the program is currently processing a prompt.
-    Processing(Instant),
-    /// Success, the program has finished processing a prompt.
-    Success,
+enum JobState {
+    /// Queued, waiting for the downloader thread to pick the job up.
+    Queued,
+    /// Processing, the downloader is currently working on the job. The payload
+    /// is the instant the current attempt started and its 1-based attempt number.
+    Processing(Instant, u32),
+    /// Done, the audio was generated and saved successfully.
+    Done,
+    /// Failed, the job could not be completed (details are shown in the error window).
+    Failed,
 }
 
-impl std::fmt::Debug for Status {
+impl std::fmt::Debug for JobState {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Status::Idle => write!(f, "Idle"),
-            Status::Processing(instant) => write!(
+            JobState::Queued => write!(f, "Queued"),
+            JobState::Processing(instant, attempt) if *attempt <= 1 => write!(
                 f,
                 "Processing [{:0>3}s / {}s]",
                 instant.elapsed().as_secs(),
                 TTS_TIMEOUT_SECONDS
             ),
-            Status::Success => write!(f, "Success"),
+            JobState::Processing(_, attempt) => {
+                write!(f, "Processing [retry {}/{}]", attempt, MAX_ATTEMPTS)
+            }
+            JobState::Done => write!(f, "Done"),
+            JobState::Failed => write!(f, "Failed"),
         }
     }
 }
 
+/// A single enqueued prompt and its progress, as tracked by the UI thread.
+struct Job {
+    /// The stable id shared with the downloader thread.
+    id: JobId,
+    /// A short label for the row (the message text, lightly truncated).
+    label: String,
+    /// The filename the audio will be saved to.
+    filename: String,
+    /// The current state of this job.
+    state: JobState,
+}
+
 /// The state of the GUI.
 pub struct VoCodesTts {
     /// The struct to submit prompts.
@@ -173,10 +455,25 @@ pub struct VoCodesTts {
     voice: &'static str,
     /// The filename to save the audio to.
     filename: String,
+    /// Whether to split long prompts into chunks and stitch the results.
+    chunk_long: bool,
+    /// The format the saved file should be transcoded to.
+    output_format: OutputFormat,
+    /// Whether to also stream each render into a voice channel.
+    voice_enabled: bool,
+    /// The `host:port` of the voice server to stream to.
+    voice_address: String,
+    /// The username to register on the voice server.
+    voice_username: String,
     /// The current error, if any.
     error: Option<Error>,
-    /// The status of the GUI.
-    status: Status,
+    /// The queue of submitted jobs, newest last.
+    jobs: Vec<Job>,
+    /// The id to assign to the next enqueued job.
+    next_id: JobId,
+    /// The audio player used to preview the last-generated clip. `None` if the
+    /// output device could not be opened at startup.
+    player: Option<AudioPlayer>,
 }
 
 impl VoCodesTts {
@@ -198,7 +495,8 @@ impl VoCodesTts {
     }
 
     /// Generates af filename for the given voice and content pair, using the first 5 words of the message to start the filename.
-    fn generate_filename(voice: &str, content: &str) -> String {
+    /// The extension follows the selected output format.
+    fn generate_filename(voice: &str, content: &str, format: OutputFormat) -> String {
         let prefix = content
             .split_whitespace()
             .take(4)
@@ -213,13 +511,23 @@ impl VoCodesTts {
 
         let date = chrono::Local::now();
         format!(
-            "{}_{}_{}.wav",
+            "{}_{}_{}.{}",
             voice,
             prefix,
-            date.format("%Y-%m-%d-%H%M%S")
+            date.format("%Y-%m-%d-%H%M%S"),
+            format.extension()
         )
     }
 
+    /// Shortens a prompt to a single-line label for display in the queue list.
+    fn truncate_label(prompt: &str) -> String {
+        const MAX: usize = 48;
+        match prompt.char_indices().nth(MAX) {
+            Some((idx, _)) => format!("{}…", &prompt[..idx]),
+            None => prompt.to_owned(),
+        }
+    }
+
     fn clean_prompt(prompt: &str) -> String {
         prompt
             .replace(|c: char| c.is_ascii_whitespace(), " ")
@@ -241,8 +549,17 @@ impl Default for VoCodesTts {
             error: None,
             voice: "sonic",
             prompt: "A test message".to_owned(),
-            filename: Self::generate_filename("sonic", "A test message"),
-            status: Status::Idle,
+            filename: Self::generate_filename("sonic", "A test message", OutputFormat::Wav),
+            chunk_long: false,
+            output_format: OutputFormat::Wav,
+            voice_enabled: false,
+            voice_address: "127.0.0.1:64738".to_owned(),
+            voice_username: "vocodes".to_owned(),
+            jobs: Vec::new(),
+            next_id: 0,
+            player: AudioPlayer::new()
+                .map_err(|e| log::warn!("Failed to open the audio output device: {}", e))
+                .ok(),
         }
     }
 }
@@ -258,33 +575,66 @@ impl epi::App for VoCodesTts {
             voice,
             prompt,
             filename,
+            chunk_long,
+            output_format,
+            voice_enabled,
+            voice_address,
+            voice_username,
             submitter,
-            status,
+            jobs,
+            next_id,
+            player,
         } = self;
 
-        match submitter.result_rx.try_recv() {
-            Ok(Ok(_)) => {
-                *status = Status::Success;
-            }
-            Err(crossbeam_channel::TryRecvError::Empty) => (),
-            Ok(Err(message)) => *error = Some(message),
-            Err(crossbeam_channel::TryRecvError::Disconnected) => {
-                *error = Some(Error {
-                    title: "Error: The downloader thread has exited unexpectedly.".into(),
-                    message:
-                        "As the message says, the downloader thread has panicked for some reason. \
-                    The application cannot continue functioning without it and must be shut down."
-                            .into(),
-                    should_exit: true,
-                    acknowledged: false,
-                });
+        // Drain every update the downloader has produced since the last frame and
+        // reconcile each one against the job it belongs to, matched by id.
+        loop {
+            match submitter.update_rx.try_recv() {
+                Ok(TtsUpdate::Attempt(id, attempt)) => {
+                    if let Some(job) = jobs.iter_mut().find(|j| j.id == id) {
+                        job.state = JobState::Processing(Instant::now(), attempt);
+                    }
+                }
+                Ok(TtsUpdate::Finished(id, result)) => {
+                    if let Some(job) = jobs.iter_mut().find(|j| j.id == id) {
+                        match result {
+                            Ok(path) => {
+                                job.state = JobState::Done;
+                                if let Some(player) = player {
+                                    if let Err(e) = player.load(&path) {
+                                        log::warn!(
+                                            "Failed to load the generated clip for playback: {}",
+                                            e
+                                        );
+                                    }
+                                }
+                            }
+                            Err(message) => {
+                                job.state = JobState::Failed;
+                                *error = Some(message);
+                            }
+                        }
+                    }
+                }
+                Err(crossbeam_channel::TryRecvError::Empty) => break,
+                Err(crossbeam_channel::TryRecvError::Disconnected) => {
+                    *error = Some(Error {
+                        title: "Error: The downloader thread has exited unexpectedly.".into(),
+                        message:
+                            "As the message says, the downloader thread has panicked for some reason. \
+                        The application cannot continue functioning without it and must be shut down."
+                                .into(),
+                        should_exit: true,
+                        acknowledged: false,
+                    });
+                    break;
+                }
             }
         }
 
         if let Some(error_value) = error {
             if error_value.acknowledged {
                 *error = None;
-                *status = Status::Idle;
             } else {
                 Self::display_error(ctx, frame, error_value)
             }
@@ -308,46 +658,142 @@ impl epi::App for VoCodesTts {
                 });
 
             ui.label("Enter your message: ");
-            if ui.text_edit_multiline(prompt).changed() || *voice != prev_voice {
-                let prompt = Self::clean_prompt(prompt);
-                *filename = Self::generate_filename(voice, &prompt);
+            let prev_format = *output_format;
+            let message_changed = ui.text_edit_multiline(prompt).changed();
+
+            ui.horizontal(|ui| {
+                ui.label("Enter the filename: ");
+                ui.text_edit_singleline(filename);
+                egui::ComboBox::from_label("Format")
+                    .selected_text(format!("{:?}", output_format))
+                    .show_ui(ui, |ui| {
+                        for format in OutputFormat::ALL.iter() {
+                            ui.selectable_value(output_format, *format, format!("{:?}", format));
+                        }
+                    });
+            });
+
+            // Regenerate the suggested filename whenever an input that feeds it changes.
+            if message_changed || *voice != prev_voice || *output_format != prev_format {
+                let cleaned = Self::clean_prompt(prompt);
+                *filename = Self::generate_filename(voice, &cleaned, *output_format);
             }
 
-            ui.label("Enter the filename: ");
-            ui.text_edit_singleline(filename);
+            ui.checkbox(
+                chunk_long,
+                format!("Split long prompts into ~{}-char chunks", MAX_CHUNK_CHARS),
+            );
+
+            ui.horizontal(|ui| {
+                ui.checkbox(voice_enabled, "Stream to voice channel");
+                if *voice_enabled {
+                    ui.text_edit_singleline(voice_address);
+                    ui.text_edit_singleline(voice_username);
+                }
+            });
 
-            if matches!(status, Status::Processing(_)) {
+            let any_processing = jobs
+                .iter()
+                .any(|j| matches!(j.state, JobState::Processing(..)));
+            if any_processing {
                 ui.output().cursor_icon = egui::CursorIcon::Progress;
             } else {
                 ui.output().cursor_icon = egui::CursorIcon::Default;
             }
 
-            ui.set_enabled(!matches!(status, Status::Processing(_)) && !prompt.is_empty());
-
-            ui.horizontal(|ui| {
-                if ui.button("Download").clicked() {
-                    if let Err(e) = submitter.prompt_tx.send(TtsPrompt {
-                        prompt: Self::clean_prompt(prompt),
-                        voice: *voice,
+            // The queue lets prompts be enqueued while others render, so the
+            // input stays live; only the Enqueue button is disabled on an empty
+            // prompt — gating the whole panel would also disable the queue list
+            // and the preview controls below.
+            if ui
+                .add_enabled(!prompt.is_empty(), egui::Button::new("Enqueue"))
+                .clicked()
+            {
+                let id = *next_id;
+                *next_id += 1;
+                let cleaned = Self::clean_prompt(prompt);
+                if let Err(e) = submitter.prompt_tx.send(TtsPrompt {
+                    id,
+                    prompt: cleaned.clone(),
+                    voice: *voice,
+                    filename: filename.clone(),
+                    chunked: *chunk_long,
+                    format: *output_format,
+                    voice_sink: voice_enabled.then(|| VoiceConfig {
+                        address: voice_address.clone(),
+                        username: voice_username.clone(),
+                    }),
+                }) {
+                    *error = Some(Error {
+                        title: "A critical error has occurred".to_string(),
+                        message: e.to_string(),
+                        should_exit: true,
+                        acknowledged: false,
+                    });
+                } else {
+                    jobs.push(Job {
+                        id,
+                        label: Self::truncate_label(&cleaned),
                         filename: filename.clone(),
-                    }) {
-                        *error = Some(Error {
-                            title: "A critical error has occurred".to_string(),
-                            message: e.to_string(),
-                            should_exit: true,
-                            acknowledged: false,
+                        state: JobState::Queued,
+                    });
+                }
+            }
+
+            if !jobs.is_empty() {
+                ui.separator();
+                ui.label("Queue:");
+                egui::ScrollArea::auto_sized().show(ui, |ui| {
+                    for job in jobs.iter() {
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::Label::new(format!("{:?}", job.state)).text_color(
+                                    match job.state {
+                                        JobState::Queued => egui::Color32::WHITE,
+                                        JobState::Processing(..) => egui::Color32::YELLOW,
+                                        JobState::Done => egui::Color32::GREEN,
+                                        JobState::Failed => egui::Color32::RED,
+                                    },
+                                ),
+                            );
+                            ui.label(&job.label).on_hover_text(&job.filename);
                         });
                     }
-                    *status = Status::Processing(Instant::now());
+                });
+                // Keep repainting while work is outstanding so elapsed times tick.
+                if any_processing {
+                    ctx.request_repaint();
                 }
-                ui.add(
-                    egui::Label::new(format!("(status: {:?})", status)).text_color(match *status {
-                        Status::Idle => egui::Color32::WHITE,
-                        Status::Processing(_) => egui::Color32::YELLOW,
-                        Status::Success => egui::Color32::GREEN,
-                    }),
-                );
-            });
+            }
+
+            if let Some(player) = player {
+                if player.is_active() || player.is_paused() {
+                    ui.separator();
+                    ui.label("Preview the last-generated clip:");
+                    ui.horizontal(|ui| {
+                        if player.is_paused() {
+                            if ui.button("Play").clicked() {
+                                player.play();
+                            }
+                        } else if ui.button("Pause").clicked() {
+                            player.pause();
+                        }
+                        if ui.button("Stop").clicked() {
+                            player.stop();
+                        }
+
+                        let position = player.position().as_secs();
+                        match player.duration() {
+                            Some(total) => {
+                                ui.label(format!("{:>3}s / {}s", position, total.as_secs()))
+                            }
+                            None => ui.label(format!("{:>3}s", position)),
+                        };
+                    });
+                    // Keep repainting so the seek position advances smoothly.
+                    ctx.request_repaint();
+                }
+            }
 
             ui.with_layout(egui::Layout::bottom_up(egui::Align::Center), |ui| {
                 ui.add(
@@ -360,3 +806,41 @@ impl epi::App for VoCodesTts {
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sentences_keep_terminators() {
+        assert_eq!(
+            split_sentences("Hi there. How are you? Great!"),
+            vec!["Hi there.", " How are you?", " Great!"],
+        );
+    }
+
+    #[test]
+    fn sentences_keep_unterminated_tail() {
+        assert_eq!(split_sentences("No terminator here"), vec!["No terminator here"]);
+    }
+
+    #[test]
+    fn chunks_pack_sentences_under_budget() {
+        // Two 9-char sentences fit in a 20-char budget; the third tips over.
+        let chunks = split_into_chunks("aaaaaaaa. bbbbbbb. ccccccc.", 20);
+        assert!(chunks.iter().all(|c| c.len() <= 20 || c.matches(' ').count() == 0));
+        assert_eq!(chunks.concat(), "aaaaaaaa. bbbbbbb. ccccccc.");
+    }
+
+    #[test]
+    fn oversized_sentence_is_emitted_whole() {
+        let long = "x".repeat(50);
+        let chunks = split_into_chunks(&long, 10);
+        assert_eq!(chunks, vec![long]);
+    }
+
+    #[test]
+    fn empty_prompt_yields_one_chunk() {
+        assert_eq!(split_into_chunks("", 300), vec![""]);
+    }
+}
@@ -0,0 +1,125 @@
+//! A small audio playback subsystem for previewing generated clips.
+//!
+//! The downloader thread does not decode audio for playback; it hands the
+//! saved file's path back to the UI thread, which loads the samples and feeds them to an
+//! output stream on demand. This mirrors the decoder→player split used by the
+//! Fancy Mumble audio stack: decoding stays off the UI thread's critical path
+//! and the player only owns the output device and the transport controls.
+
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink, Source};
+
+/// Owns the output device and the currently loaded clip.
+///
+/// The stream handle must be kept alive for the whole duration of playback,
+/// hence it lives alongside the [`Sink`] rather than being recreated per clip.
+pub struct AudioPlayer {
+    /// The output stream. Dropping it silences playback, so it is stored even
+    /// though it is never touched again after construction.
+    _stream: OutputStream,
+    /// A handle used to build a new [`Sink`] whenever a clip is loaded.
+    handle: OutputStreamHandle,
+    /// The sink playing the current clip, if any has been loaded.
+    sink: Option<Sink>,
+    /// The path of the currently loaded clip.
+    path: Option<PathBuf>,
+    /// The total duration of the current clip, if the decoder could report it.
+    duration: Option<Duration>,
+    /// The instant playback (re)started, used to estimate the seek position.
+    started: Option<Instant>,
+    /// The offset accumulated before the last pause, in seconds.
+    elapsed_before_pause: Duration,
+}
+
+impl AudioPlayer {
+    /// Opens the default output device. Returns the error as a string on failure.
+    pub fn new() -> Result<Self, String> {
+        let (stream, handle) = OutputStream::try_default().map_err(|e| e.to_string())?;
+        Ok(Self {
+            _stream: stream,
+            handle,
+            sink: None,
+            path: None,
+            duration: None,
+            started: None,
+            elapsed_before_pause: Duration::ZERO,
+        })
+    }
+
+    /// Loads a clip from disk and starts playing it immediately.
+    pub fn load(&mut self, path: &Path) -> Result<(), String> {
+        let file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+        let source = Decoder::new(BufReader::new(file)).map_err(|e| e.to_string())?;
+        self.duration = source.total_duration();
+
+        let sink = Sink::try_new(&self.handle).map_err(|e| e.to_string())?;
+        sink.append(source);
+
+        self.sink = Some(sink);
+        self.path = Some(path.to_owned());
+        self.started = Some(Instant::now());
+        self.elapsed_before_pause = Duration::ZERO;
+        Ok(())
+    }
+
+    /// Resumes playback of the loaded clip.
+    pub fn play(&mut self) {
+        if let Some(sink) = &self.sink {
+            sink.play();
+            self.started = Some(Instant::now());
+        }
+    }
+
+    /// Pauses playback, remembering the current position.
+    pub fn pause(&mut self) {
+        if let Some(sink) = &self.sink {
+            sink.pause();
+            if let Some(started) = self.started.take() {
+                self.elapsed_before_pause += started.elapsed();
+            }
+        }
+    }
+
+    /// Stops playback and drops the loaded clip.
+    pub fn stop(&mut self) {
+        if let Some(sink) = self.sink.take() {
+            sink.stop();
+        }
+        self.started = None;
+        self.elapsed_before_pause = Duration::ZERO;
+        self.duration = None;
+        self.path = None;
+    }
+
+    /// Returns `true` while a clip is loaded and has not finished playing.
+    pub fn is_active(&self) -> bool {
+        self.sink.as_ref().map_or(false, |s| !s.empty())
+    }
+
+    /// Returns `true` if a clip is loaded but paused.
+    pub fn is_paused(&self) -> bool {
+        self.sink.as_ref().map_or(false, |s| s.is_paused())
+    }
+
+    /// The estimated playback position, clamped to the clip's duration.
+    pub fn position(&self) -> Duration {
+        let mut pos = self.elapsed_before_pause;
+        if let Some(started) = self.started {
+            if !self.is_paused() {
+                pos += started.elapsed();
+            }
+        }
+        match self.duration {
+            Some(total) if pos > total => total,
+            _ => pos,
+        }
+    }
+
+    /// The total duration of the loaded clip, if known.
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+}
@@ -0,0 +1,333 @@
+//! Post-download transcoding of the raw WAV returned by the service into a
+//! more shareable compressed format.
+//!
+//! The service only ever hands back PCM WAV, which is large and awkward to
+//! share. This module decodes those samples once (via [`crate::wav::decode`])
+//! and re-encodes them with a user-selected codec, following the same
+//! decode-then-reencode pattern as the voice-bridge project's Opus path.
+
+use crate::wav;
+
+/// The output format selected in the UI. [`OutputFormat::Wav`] leaves the
+/// response untouched; the others re-encode it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OutputFormat {
+    Wav,
+    Mp3,
+    Opus,
+    Flac,
+}
+
+impl OutputFormat {
+    /// Every format, in the order they are offered in the combo box.
+    pub const ALL: [OutputFormat; 4] = [
+        OutputFormat::Wav,
+        OutputFormat::Mp3,
+        OutputFormat::Opus,
+        OutputFormat::Flac,
+    ];
+
+    /// The file extension (without the dot) produced for this format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Wav => "wav",
+            OutputFormat::Mp3 => "mp3",
+            OutputFormat::Opus => "opus",
+            OutputFormat::Flac => "flac",
+        }
+    }
+}
+
+/// Transcodes the WAV `bytes` into the given format, returning the encoded
+/// file contents. [`OutputFormat::Wav`] is a cheap pass-through.
+pub fn encode(format: OutputFormat, bytes: Vec<u8>) -> Result<Vec<u8>, String> {
+    match format {
+        OutputFormat::Wav => Ok(bytes),
+        OutputFormat::Mp3 => {
+            let (fmt, samples) = wav::decode(&bytes)?;
+            encode_mp3(fmt, &samples)
+        }
+        OutputFormat::Opus => {
+            let (fmt, samples) = wav::decode(&bytes)?;
+            encode_opus(fmt, &samples)
+        }
+        OutputFormat::Flac => {
+            let (fmt, samples) = wav::decode(&bytes)?;
+            encode_flac(fmt, &samples)
+        }
+    }
+}
+
+/// Encodes interleaved 16-bit PCM to MP3 using the LAME bindings.
+fn encode_mp3(fmt: wav::WavFormat, samples: &[i16]) -> Result<Vec<u8>, String> {
+    use mp3lame_encoder::{Bitrate, Builder, FlushNoGap, InterleavedPcm, Quality};
+
+    let mut builder = Builder::new().ok_or("failed to create the LAME encoder")?;
+    builder
+        .set_num_channels(fmt.num_channels as u8)
+        .map_err(|e| e.to_string())?;
+    builder
+        .set_sample_rate(fmt.sample_rate)
+        .map_err(|e| e.to_string())?;
+    builder
+        .set_brate(Bitrate::Kbps192)
+        .map_err(|e| e.to_string())?;
+    builder
+        .set_quality(Quality::Best)
+        .map_err(|e| e.to_string())?;
+    let mut encoder = builder.build().map_err(|e| e.to_string())?;
+
+    // `max_required_buffer_size` only budgets the `encode` call; the trailing
+    // `flush` emits up to one more MP3 frame (~7200 bytes) and would overrun a
+    // tightly-sized buffer on large clips, so reserve that headroom up front.
+    const FLUSH_HEADROOM: usize = 7200;
+    let frames = samples.len() / fmt.num_channels.max(1) as usize;
+    let mut out = Vec::with_capacity(mp3lame_encoder::max_required_buffer_size(frames) + FLUSH_HEADROOM);
+    let written = encoder
+        .encode(InterleavedPcm(samples), out.spare_capacity_mut())
+        .map_err(|e| e.to_string())?;
+    unsafe { out.set_len(written) };
+
+    out.reserve(FLUSH_HEADROOM);
+    let flushed = encoder
+        .flush::<FlushNoGap>(out.spare_capacity_mut())
+        .map_err(|e| e.to_string())?;
+    unsafe { out.set_len(out.len() + flushed) };
+    Ok(out)
+}
+
+/// The sample rate the Opus encoder runs at internally. Only 8/12/16/24/48 kHz
+/// are valid; we normalise everything to 48 kHz rather than reject the common
+/// 22.05/44.1 kHz rates the service returns.
+const OPUS_SAMPLE_RATE: u32 = 48_000;
+
+/// Encodes interleaved 16-bit PCM to an Ogg-wrapped Opus stream.
+fn encode_opus(fmt: wav::WavFormat, samples: &[i16]) -> Result<Vec<u8>, String> {
+    use opus::{Application, Channels, Encoder};
+
+    let channels = fmt.num_channels as usize;
+    let opus_channels = match fmt.num_channels {
+        1 => Channels::Mono,
+        2 => Channels::Stereo,
+        n => return Err(format!("Opus only supports mono or stereo, got {} channels", n)),
+    };
+    // The encoder only accepts a handful of rates, so resample first.
+    let resampled = resample(samples, fmt.sample_rate, OPUS_SAMPLE_RATE, channels);
+    let mut encoder = Encoder::new(OPUS_SAMPLE_RATE, opus_channels, Application::Audio)
+        .map_err(|e| e.to_string())?;
+
+    // The encoder's lookahead is the pre-skip: the number of leading 48 kHz
+    // samples a decoder must discard, carried in the `OpusHead` header and in
+    // every granule position.
+    let pre_skip = encoder.get_lookahead().map_err(|e| e.to_string())? as u64;
+
+    let mut writer = ogg::PacketWriter::new(Vec::new());
+
+    // A valid Ogg Opus stream opens with the mandatory `OpusHead` identification
+    // packet and an `OpusTags` comment packet, each alone on its own page,
+    // before any audio; players reject the stream otherwise.
+    writer
+        .write_packet(
+            opus_head(fmt.num_channels, pre_skip as u16, fmt.sample_rate).into(),
+            0,
+            ogg::PacketWriteEndInfo::EndPage,
+            0,
+        )
+        .map_err(|e| e.to_string())?;
+    writer
+        .write_packet(opus_tags().into(), 0, ogg::PacketWriteEndInfo::EndPage, 0)
+        .map_err(|e| e.to_string())?;
+
+    // Opus works on fixed 20ms frames; pad the tail with silence so the final
+    // frame is full-length.
+    let frame = (OPUS_SAMPLE_RATE as usize / 50) * channels;
+    let mut granule = pre_skip;
+    for (i, block) in resampled.chunks(frame).enumerate() {
+        let mut padded;
+        let input = if block.len() == frame {
+            block
+        } else {
+            padded = block.to_vec();
+            padded.resize(frame, 0);
+            &padded
+        };
+        let packet = encoder
+            .encode_vec(input, frame)
+            .map_err(|e| e.to_string())?;
+        granule += (frame / channels) as u64;
+        let last = (i + 1) * frame >= resampled.len();
+        let end = if last {
+            ogg::PacketWriteEndInfo::EndStream
+        } else {
+            ogg::PacketWriteEndInfo::NormalPacket
+        };
+        writer
+            .write_packet(packet.into(), 0, end, granule)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(writer.into_inner())
+}
+
+/// Builds the 19-byte `OpusHead` identification header (mapping family 0).
+fn opus_head(channels: u16, pre_skip: u16, input_rate: u32) -> Vec<u8> {
+    let mut head = Vec::with_capacity(19);
+    head.extend_from_slice(b"OpusHead");
+    head.push(1); // version
+    head.push(channels as u8);
+    head.extend_from_slice(&pre_skip.to_le_bytes());
+    head.extend_from_slice(&input_rate.to_le_bytes());
+    head.extend_from_slice(&0i16.to_le_bytes()); // output gain
+    head.push(0); // channel mapping family
+    head
+}
+
+/// Builds a minimal `OpusTags` comment header with our vendor string and no
+/// user comments.
+fn opus_tags() -> Vec<u8> {
+    const VENDOR: &[u8] = b"vocodes-tts-gui";
+    let mut tags = Vec::with_capacity(8 + 4 + VENDOR.len() + 4);
+    tags.extend_from_slice(b"OpusTags");
+    tags.extend_from_slice(&(VENDOR.len() as u32).to_le_bytes());
+    tags.extend_from_slice(VENDOR);
+    tags.extend_from_slice(&0u32.to_le_bytes()); // user comment count
+    tags
+}
+
+/// Resamples interleaved PCM from `src_rate` to `dst_rate` with linear
+/// interpolation, preserving the channel count. A no-op when the rates match.
+fn resample(samples: &[i16], src_rate: u32, dst_rate: u32, channels: usize) -> Vec<i16> {
+    let channels = channels.max(1);
+    if src_rate == dst_rate {
+        return samples.to_vec();
+    }
+    let src_frames = samples.len() / channels;
+    if src_frames == 0 {
+        return Vec::new();
+    }
+
+    let dst_frames = (src_frames as u64 * dst_rate as u64 / src_rate.max(1) as u64) as usize;
+    let ratio = src_frames as f32 / dst_frames.max(1) as f32;
+
+    let mut out = Vec::with_capacity(dst_frames * channels);
+    for i in 0..dst_frames {
+        let pos = i as f32 * ratio;
+        let idx = pos.floor() as usize;
+        let frac = pos - idx as f32;
+        let next = (idx + 1).min(src_frames - 1);
+        for ch in 0..channels {
+            let a = samples[idx * channels + ch] as f32;
+            let b = samples[next * channels + ch] as f32;
+            out.push((a + (b - a) * frac).round() as i16);
+        }
+    }
+    out
+}
+
+/// Encodes interleaved 16-bit PCM to FLAC using the pure-Rust `flacenc` crate.
+fn encode_flac(fmt: wav::WavFormat, samples: &[i16]) -> Result<Vec<u8>, String> {
+    use flacenc::component::BitRepr;
+
+    let config = flacenc::config::Encoder::default();
+    let channels = fmt.num_channels as usize;
+    let wide: Vec<i32> = samples.iter().map(|&s| s as i32).collect();
+    let source = flacenc::source::MemSource::from_samples(
+        &wide,
+        channels,
+        fmt.bits_per_sample as usize,
+        fmt.sample_rate as usize,
+    );
+    let stream = flacenc::encode_with_fixed_block_size(&config, source, config.block_size)
+        .map_err(|e| e.to_string())?;
+
+    let mut sink = flacenc::bitsink::ByteSink::new();
+    stream.write(&mut sink).map_err(|e| e.to_string())?;
+    Ok(sink.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a canonical 44-byte-header mono 16-bit WAV from `samples`.
+    fn mono_wav(sample_rate: u32, samples: &[i16]) -> Vec<u8> {
+        let mut data = Vec::with_capacity(samples.len() * 2);
+        for &s in samples {
+            data.extend_from_slice(&s.to_le_bytes());
+        }
+        let data_len = data.len() as u32;
+        let mut out = Vec::new();
+        out.extend_from_slice(b"RIFF");
+        out.extend_from_slice(&(36 + data_len).to_le_bytes());
+        out.extend_from_slice(b"WAVE");
+        out.extend_from_slice(b"fmt ");
+        out.extend_from_slice(&16u32.to_le_bytes());
+        out.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        out.extend_from_slice(&1u16.to_le_bytes()); // mono
+        out.extend_from_slice(&sample_rate.to_le_bytes());
+        out.extend_from_slice(&(sample_rate * 2).to_le_bytes()); // byte rate
+        out.extend_from_slice(&2u16.to_le_bytes()); // block align
+        out.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
+        out.extend_from_slice(b"data");
+        out.extend_from_slice(&data_len.to_le_bytes());
+        out.extend_from_slice(&data);
+        out
+    }
+
+    #[test]
+    fn resample_is_identity_at_matching_rate() {
+        let samples = [1i16, 2, 3, 4];
+        assert_eq!(resample(&samples, 48_000, 48_000, 1), samples);
+    }
+
+    #[test]
+    fn resample_scales_frame_count_by_rate_ratio() {
+        // 100 mono frames at 24 kHz → ~200 frames at 48 kHz.
+        let samples: Vec<i16> = (0..100).collect();
+        let out = resample(&samples, 24_000, 48_000, 1);
+        assert_eq!(out.len(), 200);
+    }
+
+    #[test]
+    fn opus_head_has_the_mandatory_layout() {
+        let head = opus_head(2, 312, 22_050);
+        assert_eq!(&head[0..8], b"OpusHead");
+        assert_eq!(head[8], 1); // version
+        assert_eq!(head[9], 2); // channels
+        assert_eq!(u16::from_le_bytes([head[10], head[11]]), 312); // pre-skip
+        assert_eq!(
+            u32::from_le_bytes([head[12], head[13], head[14], head[15]]),
+            22_050
+        ); // input rate
+        assert_eq!(head[18], 0); // channel mapping family
+        assert_eq!(head.len(), 19);
+    }
+
+    #[test]
+    fn opus_tags_starts_with_magic() {
+        assert_eq!(&opus_tags()[0..8], b"OpusTags");
+    }
+
+    #[test]
+    fn opus_output_is_a_valid_ogg_stream() {
+        let wav = mono_wav(22_050, &vec![0i16; 22_050]); // 1s of silence
+        let ogg = encode(OutputFormat::Opus, wav).unwrap();
+        assert_eq!(&ogg[0..4], b"OggS");
+        // The stream must carry the OpusHead identification header.
+        assert!(ogg.windows(8).any(|w| w == b"OpusHead"));
+    }
+
+    #[test]
+    fn mp3_output_is_non_empty_for_large_clips() {
+        // Enough samples that the flush tail matters.
+        let wav = mono_wav(44_100, &vec![0i16; 44_100 * 3]);
+        let mp3 = encode(OutputFormat::Mp3, wav).unwrap();
+        assert!(!mp3.is_empty());
+    }
+
+    #[test]
+    fn flac_output_starts_with_magic() {
+        let wav = mono_wav(44_100, &vec![0i16; 4_096]);
+        let flac = encode(OutputFormat::Flac, wav).unwrap();
+        assert_eq!(&flac[0..4], b"fLaC");
+    }
+}
@@ -0,0 +1,239 @@
+//! Optional voice-channel sink: stream a rendered clip live into a Mumble
+//! voice channel in addition to writing the file; the file is always written.
+//!
+//! This reuses the familiar producer→encoder→transport pipeline: the rendered
+//! PCM is resampled to 48 kHz stereo (the rate every voice stack expects),
+//! encoded into 20 ms Opus frames, and pushed to the server through a real
+//! Mumble client. The client performs the TLS control-channel handshake
+//! (`Version` + `Authenticate`) the server requires, then tunnels the voice
+//! packets over that authenticated, encrypted connection — the same UDP-tunnel
+//! fallback the official client uses when a plain UDP path is unavailable — and
+//! paces them one 20 ms frame at a time so the audio plays back in real time.
+
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use mumble_protocol::control::{msgs, ClientControlCodec, ControlPacket};
+use mumble_protocol::voice::{VoicePacket, VoicePacketPayload};
+use mumble_protocol::Serverbound;
+use tokio::net::TcpStream;
+use tokio::time::{self, MissedTickBehavior};
+use tokio_util::codec::Framed;
+
+use crate::wav;
+
+/// The sample rate every voice channel expects.
+const VOICE_SAMPLE_RATE: u32 = 48_000;
+/// Voice is always streamed as stereo.
+const VOICE_CHANNELS: usize = 2;
+/// Opus operates on 20 ms frames.
+const FRAME_SAMPLES_PER_CHANNEL: usize = VOICE_SAMPLE_RATE as usize / 50;
+/// The wall-clock duration of a single Opus frame.
+const FRAME_INTERVAL: Duration = Duration::from_millis(20);
+/// Mumble's voice sequence counts 10 ms sub-frames, so a 20 ms Opus packet
+/// advances it by two.
+const SEQ_PER_FRAME: u64 = 2;
+
+/// Connection settings for the voice sink, edited in the UI.
+#[derive(Clone, Debug)]
+pub struct VoiceConfig {
+    /// The `host:port` of the Mumble server to connect to.
+    pub address: String,
+    /// The username to register on the server.
+    pub username: String,
+}
+
+/// Resamples and encodes `wav_bytes`, then streams the Opus frames live into
+/// the configured Mumble channel.
+pub async fn stream_wav(config: &VoiceConfig, wav_bytes: &[u8]) -> Result<(), String> {
+    let (format, samples) = wav::decode(wav_bytes)?;
+    let stereo = resample_to_voice(&samples, format.sample_rate, format.num_channels as usize);
+    let frames = encode_frames(&stereo)?;
+
+    let mut sink = MumbleSink::connect(config).await?;
+    sink.stream(&frames).await?;
+    log::info!(
+        "Streamed {} Opus frames to {}",
+        frames.len(),
+        config.address
+    );
+    Ok(())
+}
+
+/// Resamples interleaved PCM to 48 kHz stereo using linear interpolation.
+///
+/// Mono input is duplicated across both channels; for anything with more than
+/// two channels only the first two are kept and the rest are dropped.
+fn resample_to_voice(samples: &[i16], src_rate: u32, src_channels: usize) -> Vec<i16> {
+    let src_channels = src_channels.max(1);
+    let src_frames = samples.len() / src_channels;
+    if src_frames == 0 {
+        return Vec::new();
+    }
+
+    // Pull the left/right source channels (duplicating mono).
+    let channel = |frame: usize, ch: usize| -> f32 {
+        let ch = ch.min(src_channels - 1);
+        samples[frame * src_channels + ch] as f32
+    };
+
+    let dst_frames =
+        (src_frames as u64 * VOICE_SAMPLE_RATE as u64 / src_rate.max(1) as u64) as usize;
+    let ratio = src_frames as f32 / dst_frames.max(1) as f32;
+
+    let mut out = Vec::with_capacity(dst_frames * VOICE_CHANNELS);
+    for i in 0..dst_frames {
+        let pos = i as f32 * ratio;
+        let idx = pos.floor() as usize;
+        let frac = pos - idx as f32;
+        let next = (idx + 1).min(src_frames - 1);
+        for ch in 0..VOICE_CHANNELS {
+            let a = channel(idx, ch);
+            let b = channel(next, ch);
+            out.push((a + (b - a) * frac).round() as i16);
+        }
+    }
+    out
+}
+
+/// Encodes interleaved 48 kHz stereo PCM into 20 ms Opus frames, padding the
+/// final frame with silence so it is full-length.
+fn encode_frames(stereo: &[i16]) -> Result<Vec<Vec<u8>>, String> {
+    use opus::{Application, Channels, Encoder};
+
+    let mut encoder = Encoder::new(VOICE_SAMPLE_RATE, Channels::Stereo, Application::Audio)
+        .map_err(|e| e.to_string())?;
+
+    let frame = FRAME_SAMPLES_PER_CHANNEL * VOICE_CHANNELS;
+    let mut frames = Vec::new();
+    for block in stereo.chunks(frame) {
+        let mut padded;
+        let input = if block.len() == frame {
+            block
+        } else {
+            padded = block.to_vec();
+            padded.resize(frame, 0);
+            &padded
+        };
+        frames.push(encoder.encode_vec(input, frame).map_err(|e| e.to_string())?);
+    }
+    Ok(frames)
+}
+
+/// A Mumble client connected over the TLS control channel. Voice frames are
+/// tunnelled through that connection, which keeps the transport encrypted and
+/// avoids a second UDP crypto handshake.
+struct MumbleSink {
+    stream: Framed<tokio_native_tls::TlsStream<TcpStream>, ClientControlCodec>,
+    /// The monotonically increasing sequence number stamped on each frame.
+    seq_num: u64,
+}
+
+impl MumbleSink {
+    /// Opens the TLS connection and performs the `Version`/`Authenticate`
+    /// handshake the server requires before it will accept voice.
+    async fn connect(config: &VoiceConfig) -> Result<Self, String> {
+        let host = config
+            .address
+            .rsplit_once(':')
+            .map(|(host, _)| host)
+            .unwrap_or(&config.address);
+
+        let tcp = TcpStream::connect(&config.address)
+            .await
+            .map_err(|e| e.to_string())?;
+        // Mumble servers commonly present self-signed certificates, so skip the
+        // chain check rather than refuse to connect.
+        let connector = native_tls::TlsConnector::builder()
+            .danger_accept_invalid_certs(true)
+            .build()
+            .map_err(|e| e.to_string())?;
+        let tls = tokio_native_tls::TlsConnector::from(connector)
+            .connect(host, tcp)
+            .await
+            .map_err(|e| e.to_string())?;
+        let mut stream = Framed::new(tls, ClientControlCodec::new());
+
+        let mut version = msgs::Version::new();
+        version.set_release("vocodes-tts-gui".to_owned());
+        stream
+            .send(version.into())
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let mut authenticate = msgs::Authenticate::new();
+        authenticate.set_username(config.username.clone());
+        authenticate.set_opus(true);
+        stream
+            .send(authenticate.into())
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let mut sink = Self { stream, seq_num: 0 };
+        // The server only places us in a channel once the handshake completes;
+        // wait for `ServerSync` (draining any control messages that precede it)
+        // before pushing voice, or the tunneled frames are dropped.
+        sink.await_server_sync().await?;
+        Ok(sink)
+    }
+
+    /// Reads the control stream until the server sends `ServerSync`, confirming
+    /// the client has been placed in a channel.
+    async fn await_server_sync(&mut self) -> Result<(), String> {
+        while let Some(packet) = self.stream.next().await {
+            match packet.map_err(|e| e.to_string())? {
+                ControlPacket::ServerSync(_) => return Ok(()),
+                ControlPacket::Reject(reject) => {
+                    return Err(format!("server rejected the connection: {:?}", reject));
+                }
+                // Ping/CryptSetup/ChannelState/etc. precede ServerSync; they
+                // are read and discarded without a reply.
+                _ => {}
+            }
+        }
+        Err("connection closed before ServerSync".to_string())
+    }
+
+    /// Streams the encoded frames in real time, one 20 ms Opus packet per tick.
+    async fn stream(&mut self, frames: &[Vec<u8>]) -> Result<(), String> {
+        let mut ticker = time::interval(FRAME_INTERVAL);
+        // If we fall behind (e.g. the server stalls), resume the 20 ms cadence
+        // from the late tick instead of bursting the missed frames.
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut i = 0;
+        while i < frames.len() {
+            // Wait for the next 20 ms slot, but keep draining the control
+            // channel (pings, state updates) meanwhile so the server keeps the
+            // session alive; only a ticker tick sends the next frame.
+            tokio::select! {
+                _ = ticker.tick() => {
+                    self.send_frame(&frames[i], i + 1 == frames.len()).await?;
+                    i += 1;
+                }
+                packet = self.stream.next() => {
+                    match packet {
+                        Some(Ok(_)) => {}
+                        Some(Err(e)) => return Err(e.to_string()),
+                        None => return Err("connection closed mid-stream".to_string()),
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Tunnels a single Opus frame through the control channel.
+    async fn send_frame(&mut self, frame: &[u8], last: bool) -> Result<(), String> {
+        let packet: VoicePacket<Serverbound> = VoicePacket::Audio {
+            _dst: std::marker::PhantomData,
+            target: 0,
+            session_id: (),
+            seq_num: self.seq_num,
+            payload: VoicePacketPayload::Opus(frame.to_vec().into(), last),
+            position_info: None,
+        };
+        self.seq_num += SEQ_PER_FRAME;
+        let tunnel: ControlPacket<Serverbound> = packet.into();
+        self.stream.send(tunnel).await.map_err(|e| e.to_string())
+    }
+}
@@ -0,0 +1,227 @@
+//! Minimal RIFF/WAVE handling used to stitch the per-chunk responses of a long
+//! prompt back into a single clip.
+//!
+//! The service returns a self-contained `.wav` for every request, so to join
+//! them we parse each one's header, confirm they share a PCM format, keep only
+//! the `data` payloads, and emit a fresh canonical 44-byte header whose sizes
+//! cover the concatenated audio.
+
+/// The PCM parameters carried by a `fmt ` chunk that must match across all
+/// chunks before they can be concatenated.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct WavFormat {
+    pub num_channels: u16,
+    pub sample_rate: u32,
+    pub bits_per_sample: u16,
+}
+
+/// Decodes a 16-bit PCM WAV into its format and interleaved samples, for
+/// feeding to an encoder. Non-16-bit streams are rejected, as the encoders we
+/// target all consume `i16`.
+pub fn decode(bytes: &[u8]) -> Result<(WavFormat, Vec<i16>), String> {
+    let (format, data) = parse(bytes)?;
+    if format.bits_per_sample != 16 {
+        return Err(format!(
+            "unsupported bit depth: {} (only 16-bit PCM is supported)",
+            format.bits_per_sample
+        ));
+    }
+    let samples = data
+        .chunks_exact(2)
+        .map(|b| i16::from_le_bytes([b[0], b[1]]))
+        .collect();
+    Ok((format, samples))
+}
+
+/// Reads a little-endian `u16` at `offset`, or returns a parse error.
+fn read_u16(bytes: &[u8], offset: usize) -> Result<u16, String> {
+    bytes
+        .get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or_else(|| "truncated WAV: expected a 16-bit field".to_string())
+}
+
+/// Reads a little-endian `u32` at `offset`, or returns a parse error.
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, String> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| "truncated WAV: expected a 32-bit field".to_string())
+}
+
+/// Parses a single WAV blob, returning its format and the `data` payload.
+///
+/// Non-`data` subchunks that follow `fmt ` (e.g. `LIST`) are skipped when
+/// locating the payload, as mandated by the RIFF chunk layout.
+fn parse(bytes: &[u8]) -> Result<(WavFormat, &[u8]), String> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err("not a RIFF/WAVE file".to_string());
+    }
+
+    let mut format = None;
+    let mut data = None;
+
+    // Walk the subchunk list that starts right after the `WAVE` tag.
+    let mut offset = 12;
+    while offset + 8 <= bytes.len() {
+        let id = &bytes[offset..offset + 4];
+        let size = read_u32(bytes, offset + 4)? as usize;
+        let body = offset + 8;
+        let end = body
+            .checked_add(size)
+            .filter(|&e| e <= bytes.len())
+            .ok_or_else(|| "truncated WAV: subchunk runs past end of file".to_string())?;
+
+        match id {
+            b"fmt " => {
+                format = Some(WavFormat {
+                    num_channels: read_u16(bytes, body + 2)?,
+                    sample_rate: read_u32(bytes, body + 4)?,
+                    bits_per_sample: read_u16(bytes, body + 14)?,
+                });
+            }
+            b"data" => {
+                data = Some(&bytes[body..end]);
+            }
+            _ => {}
+        }
+
+        // Subchunks are word-aligned: an odd size is followed by a pad byte.
+        offset = end + (size & 1);
+    }
+
+    match (format, data) {
+        (Some(format), Some(data)) => Ok((format, data)),
+        (None, _) => Err("WAV is missing a `fmt ` chunk".to_string()),
+        (_, None) => Err("WAV is missing a `data` chunk".to_string()),
+    }
+}
+
+/// Concatenates several WAV blobs into one.
+///
+/// All inputs must share the same sample rate, channel count and bit depth;
+/// a mismatch aborts with an error rather than producing a garbled clip.
+pub fn stitch(chunks: &[Vec<u8>]) -> Result<Vec<u8>, String> {
+    let mut format: Option<WavFormat> = None;
+    let mut payload = Vec::new();
+
+    for chunk in chunks {
+        let (fmt, data) = parse(chunk)?;
+        match format {
+            Some(expected) if expected != fmt => {
+                return Err(format!(
+                    "WAV format mismatch: expected {:?}, got {:?}",
+                    expected, fmt
+                ));
+            }
+            _ => format = Some(fmt),
+        }
+        payload.extend_from_slice(data);
+    }
+
+    let format = format.ok_or_else(|| "nothing to stitch: no WAV chunks".to_string())?;
+    Ok(write_canonical(format, &payload))
+}
+
+/// Builds a canonical 44-byte-header PCM WAV from a format and its payload.
+fn write_canonical(format: WavFormat, payload: &[u8]) -> Vec<u8> {
+    let byte_rate =
+        format.sample_rate * format.num_channels as u32 * (format.bits_per_sample as u32 / 8);
+    let block_align = format.num_channels * (format.bits_per_sample / 8);
+    let data_len = payload.len() as u32;
+
+    let mut out = Vec::with_capacity(44 + payload.len());
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&(36 + data_len).to_le_bytes());
+    out.extend_from_slice(b"WAVE");
+    out.extend_from_slice(b"fmt ");
+    out.extend_from_slice(&16u32.to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    out.extend_from_slice(&format.num_channels.to_le_bytes());
+    out.extend_from_slice(&format.sample_rate.to_le_bytes());
+    out.extend_from_slice(&byte_rate.to_le_bytes());
+    out.extend_from_slice(&block_align.to_le_bytes());
+    out.extend_from_slice(&format.bits_per_sample.to_le_bytes());
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&data_len.to_le_bytes());
+    out.extend_from_slice(payload);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FMT: WavFormat = WavFormat {
+        num_channels: 1,
+        sample_rate: 22_050,
+        bits_per_sample: 16,
+    };
+
+    #[test]
+    fn canonical_round_trips() {
+        let payload = [1u8, 2, 3, 4, 5, 6];
+        let wav = write_canonical(FMT, &payload);
+        assert_eq!(&wav[0..4], b"RIFF");
+        assert_eq!(&wav[8..12], b"WAVE");
+        // The RIFF size covers everything after the first 8 bytes, the data
+        // size is exactly the payload length.
+        assert_eq!(read_u32(&wav, 4).unwrap() as usize, 36 + payload.len());
+        assert_eq!(read_u32(&wav, 40).unwrap() as usize, payload.len());
+
+        let (fmt, data) = parse(&wav).unwrap();
+        assert_eq!(fmt, FMT);
+        assert_eq!(data, &payload);
+    }
+
+    #[test]
+    fn decode_rejects_non_16_bit() {
+        let eight_bit = WavFormat {
+            bits_per_sample: 8,
+            ..FMT
+        };
+        let wav = write_canonical(eight_bit, &[0, 0]);
+        assert!(decode(&wav).is_err());
+    }
+
+    #[test]
+    fn parse_skips_trailing_non_data_subchunks() {
+        // A canonical WAV with a `LIST` subchunk spliced in before `data`.
+        let mut wav = write_canonical(FMT, &[]);
+        // Drop the empty `data` header we just wrote and rebuild with LIST first.
+        wav.truncate(36);
+        wav.extend_from_slice(b"LIST");
+        wav.extend_from_slice(&4u32.to_le_bytes());
+        wav.extend_from_slice(b"INFO");
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&2u32.to_le_bytes());
+        wav.extend_from_slice(&[7, 8]);
+        // Fix up the RIFF size to cover the appended bytes.
+        let riff = (wav.len() - 8) as u32;
+        wav[4..8].copy_from_slice(&riff.to_le_bytes());
+
+        let (fmt, data) = parse(&wav).unwrap();
+        assert_eq!(fmt, FMT);
+        assert_eq!(data, &[7, 8]);
+    }
+
+    #[test]
+    fn stitch_concatenates_matching_chunks() {
+        let a = write_canonical(FMT, &[1, 2]);
+        let b = write_canonical(FMT, &[3, 4]);
+        let joined = stitch(&[a, b]).unwrap();
+        let (_, data) = parse(&joined).unwrap();
+        assert_eq!(data, &[1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn stitch_aborts_on_format_mismatch() {
+        let a = write_canonical(FMT, &[1, 2]);
+        let other = WavFormat {
+            sample_rate: 44_100,
+            ..FMT
+        };
+        let b = write_canonical(other, &[3, 4]);
+        assert!(stitch(&[a, b]).is_err());
+    }
+}